@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::decimal::Decimal;
+use crate::structs::{Operation, Record};
+
+// Seeds the chain so a fresh ledger always starts from the same hash instead
+// of an arbitrary all-zero value.
+const GENESIS: &[u8] = b"simple_ledger_cli/audit-log/genesis";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hash([u8; 32]);
+
+impl Hash {
+    fn genesis() -> Hash {
+        Hash(Sha256::digest(GENESIS).into())
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Hash {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+// One accepted transaction in the audit trail. `hash` folds in `prev_hash`
+// plus this entry's own fields, so replaying the chain from genesis is the
+// only way to reproduce it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainEntry {
+    r#type: Operation,
+    client: u16,
+    tx: u32,
+    amount: Option<Decimal>,
+    hash: Hash,
+}
+
+impl ChainEntry {
+    fn hash(prev_hash: Hash, r#type: &Operation, client: u16, tx: u32, amount: Option<Decimal>) -> Hash {
+        let mut hasher = Sha256::new();
+        hasher.update(prev_hash.0);
+        hasher.update(r#type.as_str().as_bytes());
+        hasher.update(client.to_le_bytes());
+        hasher.update(tx.to_le_bytes());
+        if let Some(amount) = amount {
+            hasher.update(amount.to_string().as_bytes());
+        }
+        Hash(hasher.finalize().into())
+    }
+}
+
+// Append-only log of every transaction the ledger has accepted, chained by
+// hash so that altering or reordering a past entry changes every hash after
+// it. Lives alongside `Ledger`'s client map: both grow from the same stream
+// of accepted records.
+#[derive(Default)]
+pub struct HashChain {
+    entries: Vec<ChainEntry>,
+}
+
+impl HashChain {
+    pub fn new() -> HashChain {
+        HashChain::default()
+    }
+
+    fn last_hash(&self) -> Hash {
+        self.entries.last().map(|e| e.hash).unwrap_or_else(Hash::genesis)
+    }
+
+    // Record an accepted transaction as the next link in the chain.
+    pub fn append(&mut self, record: &Record) {
+        let hash = ChainEntry::hash(self.last_hash(), &record.r#type, record.client, record.tx, record.amount());
+        self.entries.push(ChainEntry {
+            r#type: record.r#type.clone(),
+            client: record.client,
+            tx: record.tx,
+            amount: record.amount(),
+            hash,
+        });
+    }
+
+    // Walk the chain from genesis and confirm every recorded hash is
+    // reproducible from its predecessor. Returns the index of the first
+    // entry that doesn't reproduce, if any.
+    pub fn verify(&self) -> Result<(), usize> {
+        let mut prev_hash = Hash::genesis();
+        for (i, entry) in self.entries.iter().enumerate() {
+            let expected = ChainEntry::hash(prev_hash, &entry.r#type, entry.client, entry.tx, entry.amount);
+            if expected != entry.hash {
+                return Err(i);
+            }
+            prev_hash = entry.hash;
+        }
+        Ok(())
+    }
+
+    // Write the chain as CSV to `writer`, one row per accepted transaction.
+    pub fn dump_csv<W: Write>(&self, writer: W) -> Result<(), Box<dyn Error>> {
+        let mut wtr = csv::Writer::from_writer(writer);
+        for entry in &self.entries {
+            wtr.serialize(entry)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    fn deposit(tx: u32, client: u16, amount: &str) -> Record {
+        let row = csv::StringRecord::from(vec!["deposit", &client.to_string(), &tx.to_string(), amount]);
+        Record::try_from(&row).unwrap()
+    }
+
+    #[test]
+    fn verifies_an_untouched_chain() {
+        let mut chain = HashChain::new();
+        chain.append(&deposit(1, 1, "1"));
+        chain.append(&deposit(2, 1, "2"));
+        assert_eq!(chain.verify(), Ok(()));
+    }
+
+    #[test]
+    fn detects_a_tampered_entry() {
+        let mut chain = HashChain::new();
+        chain.append(&deposit(1, 1, "1"));
+        chain.append(&deposit(2, 1, "2"));
+        chain.entries[0].amount = Some("999".parse().unwrap());
+        assert_eq!(chain.verify(), Err(0));
+    }
+
+    #[test]
+    fn same_transactions_reproduce_the_same_chain() {
+        let mut a = HashChain::new();
+        a.append(&deposit(1, 1, "1"));
+        a.append(&deposit(2, 1, "2"));
+
+        let mut b = HashChain::new();
+        b.append(&deposit(1, 1, "1"));
+        b.append(&deposit(2, 1, "2"));
+
+        assert_eq!(a.entries.last().unwrap().hash, b.entries.last().unwrap().hash);
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
+
+// Amounts are stored as a whole number of ten-thousandths of a unit so that
+// additions/subtractions across deposits, withdrawals and disputes stay
+// exact instead of accumulating binary floating-point error.
+const SCALE: i64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Decimal(i64);
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal(0);
+}
+
+#[derive(Debug)]
+pub struct ParseDecimalError;
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid decimal amount")
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let mut parts = s.splitn(2, '.');
+        let int_part = parts.next().unwrap_or("");
+        let frac_part = parts.next().unwrap_or("");
+        if int_part.is_empty() && frac_part.is_empty() {
+            return Err(ParseDecimalError);
+        }
+
+        let integer: i64 = if int_part.is_empty() {
+            0
+        } else {
+            int_part.parse().map_err(|_| ParseDecimalError)?
+        };
+
+        // Only the first four fractional digits are significant; anything
+        // past that is truncated rather than rounded.
+        let mut fraction: i64 = 0;
+        for (i, c) in frac_part.chars().take(4).enumerate() {
+            let digit = c.to_digit(10).ok_or(ParseDecimalError)? as i64;
+            fraction += digit * 10i64.pow(3 - i as u32);
+        }
+        if frac_part.len() > 4 && !frac_part.chars().skip(4).all(|c| c.is_ascii_digit()) {
+            return Err(ParseDecimalError);
+        }
+
+        let magnitude = integer * SCALE + fraction;
+        Ok(Decimal(if negative { -magnitude } else { magnitude }))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let magnitude = self.0.unsigned_abs();
+        let integer = magnitude / SCALE as u64;
+        let fraction = magnitude % SCALE as u64;
+        if fraction == 0 {
+            write!(f, "{}{}", sign, integer)
+        } else {
+            let mut frac_str = format!("{:04}", fraction);
+            while frac_str.ends_with('0') {
+                frac_str.pop();
+            }
+            write!(f, "{}{}.{}", sign, integer, frac_str)
+        }
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+    fn add(self, rhs: Decimal) -> Decimal {
+        Decimal(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+    fn sub(self, rhs: Decimal) -> Decimal {
+        Decimal(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for Decimal {
+    fn add_assign(&mut self, rhs: Decimal) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Decimal {
+    fn sub_assign(&mut self, rhs: Decimal) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays() {
+        assert_eq!("1".parse::<Decimal>().unwrap().to_string(), "1");
+        assert_eq!("1.5".parse::<Decimal>().unwrap().to_string(), "1.5");
+        assert_eq!("1.5000".parse::<Decimal>().unwrap().to_string(), "1.5");
+        assert_eq!("0.0001".parse::<Decimal>().unwrap().to_string(), "0.0001");
+        assert_eq!("-2.3".parse::<Decimal>().unwrap().to_string(), "-2.3");
+        assert!("abc".parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn arithmetic_is_exact() {
+        let mut total = Decimal::ZERO;
+        for _ in 0..3 {
+            total += "0.1".parse::<Decimal>().unwrap();
+        }
+        assert_eq!(total.to_string(), "0.3");
+    }
+}
@@ -0,0 +1,50 @@
+use std::error::Error;
+use std::fmt;
+
+// Reasons a transaction was rejected by the dispute state machine in `Client`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LedgerError {
+    UnknownTx,
+    AlreadyDisputed,
+    NotDisputed,
+    FrozenAccount,
+    NotEnoughFunds,
+}
+
+impl LedgerError {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LedgerError::UnknownTx => "no such transaction",
+            LedgerError::AlreadyDisputed => "transaction is already disputed or resolved",
+            LedgerError::NotDisputed => "transaction is not under dispute",
+            LedgerError::FrozenAccount => "account is locked",
+            LedgerError::NotEnoughFunds => "not enough available funds",
+        }
+    }
+}
+
+impl Error for LedgerError {}
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// Reasons a raw CSV row failed to become a `Record`, see `Record`'s `TryFrom` impl.
+#[derive(Debug)]
+pub enum RecordParseError {
+    MissingField(&'static str),
+    InvalidField(&'static str),
+    UnknownOperation(String),
+}
+
+impl Error for RecordParseError {}
+impl fmt::Display for RecordParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordParseError::MissingField(field) => write!(f, "missing '{}' field", field),
+            RecordParseError::InvalidField(field) => write!(f, "invalid '{}' field", field),
+            RecordParseError::UnknownOperation(op) => write!(f, "unknown operation '{}'", op),
+        }
+    }
+}
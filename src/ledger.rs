@@ -0,0 +1,74 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::Write;
+
+use crate::audit::HashChain;
+use crate::errors::LedgerError;
+use crate::structs::{Client, Operation, Record};
+
+// Holds all client accounts and processes transactions one at a time. This is
+// the reusable core behind both the one-shot CSV batch mode and the long
+// running server mode: both just feed `Record`s into the same `Ledger`.
+pub struct Ledger {
+    clients: HashMap<u16, Client>,
+    tx_set: HashSet<u32>,
+    should_block_locked: bool,
+    audit: HashChain
+}
+
+impl Ledger {
+    pub fn new(should_block_locked: bool) -> Ledger {
+        Ledger {
+            clients: HashMap::new(),
+            tx_set: HashSet::new(),
+            should_block_locked,
+            audit: HashChain::new()
+        }
+    }
+
+    // Apply a single transaction to the ledger. Duplicate deposit/withdrawal
+    // tx ids and records referencing an unknown client are silently ignored,
+    // matching `Record::is_valid`'s "best effort" philosophy; illegal dispute
+    // transitions are reported through `LedgerError`. Every transaction that
+    // reaches a client and is accepted is appended to the audit chain.
+    pub fn process(&mut self, record: Record) -> Result<(), LedgerError> {
+        if record.r#type == Operation::Deposit || record.r#type == Operation::Withdrawal {
+            if self.tx_set.contains(&record.tx) {
+                return Ok(());
+            }
+            self.tx_set.insert(record.tx);
+        }
+
+        if let Some(client) = self.clients.get_mut(&record.client) {
+            client.handle_record(&record, self.should_block_locked)?;
+        } else if record.r#type == Operation::Deposit {
+            let client = Client::from_record(&record).expect("record type already checked to be Deposit");
+            self.clients.insert(record.client, client);
+        } else {
+            return Ok(());
+        }
+
+        self.audit.append(&record);
+        Ok(())
+    }
+
+    // Write every client account as CSV to `writer`.
+    pub fn dump_csv<W: Write>(&self, writer: W) -> Result<(), Box<dyn Error>> {
+        let mut wtr = csv::Writer::from_writer(writer);
+        for client in self.clients.values() {
+            wtr.serialize(client)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    // Write the audit chain as CSV to `writer`, one row per accepted transaction.
+    pub fn dump_chain_csv<W: Write>(&self, writer: W) -> Result<(), Box<dyn Error>> {
+        self.audit.dump_csv(writer)
+    }
+
+    // Confirm the audit chain hasn't been tampered with, see `HashChain::verify`.
+    pub fn verify(&self) -> Result<(), usize> {
+        self.audit.verify()
+    }
+}
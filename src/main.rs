@@ -1,76 +1,110 @@
-use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::error::Error;
 use std::env;
 use std::fs::File;
 use std::process;
 use std::ffi::OsString;
 use std::io;
+use std::sync::{Arc, Mutex};
+
+mod audit;
+mod decimal;
+mod errors;
+mod ledger;
+mod server;
 
 mod structs;
 use crate::structs::*;
+use crate::ledger::Ledger;
 
-mod utils;
+fn should_block_locked() -> bool {
+    match env::var("BLOCK_LOCKED_ACCOUNTS") {
+        Ok(val) => val == "true",
+        _ => false
+    }
+}
+
+fn should_log_rejected() -> bool {
+    match env::var("LOG_REJECTED_ROWS") {
+        Ok(val) => val == "true",
+        _ => false
+    }
+}
+
+fn should_emit_chain() -> bool {
+    match env::var("EMIT_CHAIN") {
+        Ok(val) => val == "true",
+        _ => false
+    }
+}
 
 // Read the CSV and loop through each line to process transactions
 fn handle_csv() -> Result<(), Box<dyn Error>> {
     let file_path = get_first_arg()?;
     let file = File::open(file_path)?;
-    let mut rdr = csv::Reader::from_reader(file);
-
-    let mut raw_record = csv::ByteRecord::new();
-    let mut headers = rdr.byte_headers()?.clone();
-    headers.trim();
-    // mapping client with their ids to facilitate account and operation management
-    let mut clients_map: HashMap<u16, Client> = HashMap::new();
-    // store tx ids to avoid duplicates
-    let mut tx_set: HashSet<u32> = HashSet::new();
-    // locked accounts behaviour
-    let should_block_locked = match env::var("BLOCK_LOCKED_ACCOUNTS") {
-        Ok(val) => val == "true",
-        _ => false
-    };
+    // `flexible` lets dispute/resolve/chargeback rows omit the trailing amount
+    // column; rows are read positionally rather than via serde's header match,
+    // see Record's TryFrom impl.
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(file);
 
-    while rdr.read_byte_record(&mut raw_record)? {
-        raw_record.trim();
-        let record: Record = raw_record.deserialize(Some(&headers))?;
+    let mut ledger = Ledger::new(should_block_locked());
+    let should_log_rejected = should_log_rejected();
+    let mut skipped_rows: u64 = 0;
 
-        // ignore invalid records or transaction already handled
-        if !record.is_valid() {
-            continue;
-        }
-        if record.r#type == Operation::Deposit || record.r#type == Operation::Withdrawal {
-            if tx_set.contains(&record.tx) {
+    for row in rdr.records() {
+        let row = row?;
+        let record = match Record::try_from(&row) {
+            Ok(record) => record,
+            Err(err) => {
+                skipped_rows += 1;
+                if should_log_rejected {
+                    eprintln!("skipping malformed row: {}", err);
+                }
                 continue;
-            } else {
-                tx_set.insert(record.tx);
             }
+        };
+
+        // ignore invalid records
+        if !record.is_valid() {
+            skipped_rows += 1;
+            continue;
         }
 
-        // handle record and associated client/operation
-        // if no client found for the record and if the operation is a new deposit, create a client
-        if let Some(client) = clients_map.get_mut(&record.client) {
-            client.handle_record(&record, should_block_locked);
-        } else if record.r#type == Operation::Deposit {
-            let client = Client::from_record(&record)?;
-            clients_map.insert(record.client, client);
+        let (tx, client) = (record.tx, record.client);
+        if let Err(err) = ledger.process(record) {
+            skipped_rows += 1;
+            if should_log_rejected {
+                eprintln!("rejected tx {} for client {}: {}", tx, client, err);
+            }
         }
     }
-    output_clients(clients_map.into_values().collect())
-}
-
-// Output result, client accounts, as CSV
-fn output_clients(clients: Vec<Client>) -> Result<(), Box<dyn Error>> {
-    let mut wtr = csv::Writer::from_writer(io::stdout());
 
-    // When writing records with Serde using structs, the header row is written automatically.
-    for c in clients {
-        wtr.serialize(c)?;
+    ledger.dump_csv(io::stdout())?;
+    if skipped_rows > 0 {
+        eprintln!("skipped {} row(s)", skipped_rows);
     }
 
-    wtr.flush()?;
+    // the audit chain is diagnostic output, so it goes alongside the account
+    // CSV on stderr rather than mixed into stdout's single-schema table
+    if should_emit_chain() {
+        ledger.dump_chain_csv(io::stderr())?;
+        if let Err(i) = ledger.verify() {
+            eprintln!("audit chain broken at entry {}", i);
+        }
+    }
     Ok(())
 }
 
+// Run as a long-lived server: transactions are streamed in over TCP and
+// applied to a single shared ledger instead of being read from a file once.
+fn run_server(addr: &str) -> Result<(), Box<dyn Error>> {
+    let ledger = Arc::new(Mutex::new(Ledger::new(should_block_locked())));
+    server::serve(addr, ledger)
+}
+
 // Returns the first positional argument sent to this process. If there are no positional arguments, then this returns an error.
 fn get_first_arg() -> Result<OsString, Box<dyn Error>> {
     match env::args_os().nth(1) {
@@ -80,11 +114,16 @@ fn get_first_arg() -> Result<OsString, Box<dyn Error>> {
 }
 
 fn main() {
-    match handle_csv() {
-        Ok(_) => {},
-        Err(err) => {
-            println!("{}", err);
-            process::exit(1);
+    let result = match env::args().nth(1).as_deref() {
+        Some("--serve") => {
+            let addr = env::args().nth(2).unwrap_or_else(|| "127.0.0.1:7878".to_string());
+            run_server(&addr)
         }
+        _ => handle_csv()
+    };
+
+    if let Err(err) = result {
+        println!("{}", err);
+        process::exit(1);
     }
 }
@@ -0,0 +1,62 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use crate::ledger::Ledger;
+use crate::structs::Record;
+
+// Line-oriented protocol: a connection sends either CSV transaction rows, one
+// per line, or one of the commands `snapshot`/`chain` to get the current
+// account CSV / audit chain CSV written back on that same connection. All
+// connections share one `Ledger`.
+pub fn serve(addr: &str, ledger: Arc<Mutex<Ledger>>) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr)?;
+    eprintln!("listening on {}", addr);
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        if let Err(err) = handle_connection(stream, Arc::clone(&ledger)) {
+            eprintln!("connection error: {}", err);
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, ledger: Arc<Mutex<Ledger>>) -> Result<(), Box<dyn Error>> {
+    let mut writer = stream.try_clone()?;
+    let reader = BufReader::new(stream);
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.eq_ignore_ascii_case("snapshot") {
+            let ledger = ledger.lock().unwrap();
+            ledger.dump_csv(&mut writer)?;
+            continue;
+        }
+
+        if line.eq_ignore_ascii_case("chain") {
+            let ledger = ledger.lock().unwrap();
+            ledger.dump_chain_csv(&mut writer)?;
+            continue;
+        }
+
+        let row = csv::StringRecord::from(line.split(',').collect::<Vec<_>>());
+        match Record::try_from(&row) {
+            Ok(record) if record.is_valid() => {
+                let mut ledger = ledger.lock().unwrap();
+                // illegal dispute transitions are rejected the same way the
+                // batch mode rejects them; the connection just moves on
+                let _ = ledger.process(record);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
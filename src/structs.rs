@@ -1,11 +1,12 @@
 
 use std::fmt;
+use std::convert::TryFrom;
 use crate::Error;
-use serde::{Serialize, Deserialize};
-use crate::utils::round_serialize;
+use serde::Serialize;
+use crate::decimal::Decimal;
+use crate::errors::{LedgerError, RecordParseError};
 
-#[derive(Debug, Deserialize, Clone, PartialEq)]
-#[serde(rename_all = "lowercase")]
+#[derive(Debug, Clone, PartialEq)]
 // represents an operation type for a given transaction
 pub enum Operation {
     Deposit,
@@ -16,7 +17,7 @@ pub enum Operation {
 }
 
 impl Operation {
-    fn as_str(&self) -> &'static str {
+    pub(crate) fn as_str(&self) -> &'static str {
         match self {
             Operation::Deposit => "deposit",
             Operation::Withdrawal => "withdrawal",
@@ -27,55 +28,128 @@ impl Operation {
     }
 }
 
-#[derive(Debug, Deserialize)]
+impl Serialize for Operation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl TryFrom<&str> for Operation {
+    type Error = RecordParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "deposit" => Ok(Operation::Deposit),
+            "withdrawal" => Ok(Operation::Withdrawal),
+            "dispute" => Ok(Operation::Dispute),
+            "resolve" => Ok(Operation::Resolve),
+            "chargeback" => Ok(Operation::Chargeback),
+            other => Err(RecordParseError::UnknownOperation(other.to_string()))
+        }
+    }
+}
+
+#[derive(Debug)]
 // A CSV line representing a transaction
 pub struct Record {
     pub r#type: Operation,
     pub client: u16,
     pub tx: u32,
-    amount: Option<f64>,
+    amount: Option<Decimal>,
 }
 
 impl Record {
+    pub(crate) fn amount(&self) -> Option<Decimal> {
+        self.amount
+    }
+
     pub fn is_valid(&self) -> bool {
         if self.amount.is_none() && (self.r#type == Operation::Deposit || self.r#type == Operation::Withdrawal) {
             return false;
         } else if let Some(amount) = self.amount {
-            return amount > 0.0;
+            return amount > Decimal::ZERO;
         }
         true
     }
 }
 
+impl TryFrom<&csv::StringRecord> for Record {
+    type Error = RecordParseError;
+
+    // Rows are read positionally (type, client, tx, amount) rather than through
+    // serde's header-matching so that dispute/resolve/chargeback rows, which
+    // legally omit the trailing amount column, don't need a header lookup.
+    fn try_from(row: &csv::StringRecord) -> Result<Self, Self::Error> {
+        let r#type = row.get(0).ok_or(RecordParseError::MissingField("type"))?;
+        let r#type = Operation::try_from(r#type)?;
+
+        let client = row.get(1).ok_or(RecordParseError::MissingField("client"))?;
+        let client = client.parse().map_err(|_| RecordParseError::InvalidField("client"))?;
+
+        let tx = row.get(2).ok_or(RecordParseError::MissingField("tx"))?;
+        let tx = tx.parse().map_err(|_| RecordParseError::InvalidField("tx"))?;
+
+        let amount = match row.get(3) {
+            Some(raw) if !raw.is_empty() => Some(raw.parse().map_err(|_| RecordParseError::InvalidField("amount"))?),
+            _ => None
+        };
+
+        Ok(Record { r#type, client, tx, amount })
+    }
+}
+
+// The legal transitions are Processed -> Disputed, Disputed -> Resolved and
+// Disputed -> ChargedBack; anything else is rejected by `new_dispute`/`resolve_dispute`.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum TxState {
+    Processed,
+    Disputed,
+    Resolved,
+    ChargedBack
+}
+
+// Disputes/resolves/chargebacks can reference either a deposit or a withdrawal;
+// `kind` decides how `Client::new_dispute`/`resolve_dispute` move the balances.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum TxKind {
+    Deposit,
+    Withdrawal
+}
+
 #[derive(Debug, Serialize)]
-pub struct ClientDeposit {
+pub struct ClientTx {
     tx: u32,
-    amount: f64,
-    is_disputed: bool,
-    is_dispute_handled: bool
+    kind: TxKind,
+    amount: Decimal,
+    state: TxState
 }
 
-impl ClientDeposit {
-    pub fn from_record(record: &Record) -> ClientDeposit {
-        ClientDeposit { tx: record.tx, amount: record.amount.expect("Expecting an amount from record"), is_disputed: false, is_dispute_handled: false }
+impl ClientTx {
+    pub fn from_record(record: &Record) -> ClientTx {
+        let kind = match record.r#type {
+            Operation::Deposit => TxKind::Deposit,
+            Operation::Withdrawal => TxKind::Withdrawal,
+            ref op => panic!("Cannot track a {} as a client transaction", op.as_str())
+        };
+        ClientTx { tx: record.tx, kind, amount: record.amount.expect("Expecting an amount from record"), state: TxState::Processed }
     }
 }
 
 #[derive(Debug, Serialize)]
-// Represents a client account, we store client deposits to handle disputes
+// Represents a client account, we store client transactions to handle disputes
 // All the transactions are handled through a Client, you should avoid handling record without Client::handle_record
 // Records must be valid and we assume that they have been checked before, see Record::is_valid
 pub struct Client {
     #[serde(rename = "client")]
     id: u16,
     #[serde(skip_serializing)]
-    deposits: Vec<ClientDeposit>,
-    #[serde(serialize_with = "round_serialize")]
-    available: f64,
-    #[serde(serialize_with = "round_serialize")]
-    held: f64,
-    #[serde(serialize_with = "round_serialize")]
-    total: f64,
+    transactions: Vec<ClientTx>,
+    available: Decimal,
+    held: Decimal,
+    total: Decimal,
     locked: bool
 }
 
@@ -88,71 +162,107 @@ impl Client {
                 Ok(Client {
                     id: record.client,
                     available: total,
-                    held: 0.0,
+                    held: Decimal::ZERO,
                     total,
                     locked: false,
-                    deposits: vec![ClientDeposit::from_record(record)]
+                    transactions: vec![ClientTx::from_record(record)]
                 })
             }
             op => Err(Box::new(BadRecordForClientCreation { operation: op.clone() }))
         }
     }
 
-    fn deposit_amount(&mut self, amount: f64) {
+    fn deposit_amount(&mut self, amount: Decimal) {
         self.available += amount;
         self.total += amount;
     }
-    fn withdraw_amount(&mut self, amount: f64) {
+    // returns whether the withdrawal was actually applied, so the caller can
+    // decide whether to track it for future disputes
+    fn withdraw_amount(&mut self, amount: Decimal) -> bool {
         if amount <= self.available {
             self.available -= amount;
             self.total -= amount;
+            true
+        } else {
+            false
         }
     }
-    fn new_dispute(&mut self, tx: u32) {
-        if let Some(deposit) = self.deposits.iter_mut().find(|d| d.tx == tx) {
-            if deposit.is_disputed || deposit.is_dispute_handled {
-                return
+    fn new_dispute(&mut self, tx: u32) -> Result<(), LedgerError> {
+        let transaction = self.transactions.iter_mut().find(|t| t.tx == tx).ok_or(LedgerError::UnknownTx)?;
+        if transaction.state != TxState::Processed {
+            return Err(LedgerError::AlreadyDisputed);
+        }
+        let amount = transaction.amount;
+        let kind = transaction.kind;
+        if kind == TxKind::Deposit && self.available < amount {
+            return Err(LedgerError::NotEnoughFunds);
+        }
+        transaction.state = TxState::Disputed;
+
+        match kind {
+            // a disputed deposit is frozen: move it out of available into held
+            TxKind::Deposit => {
+                self.available -= amount;
+                self.held += amount;
             }
-            if self.available >= deposit.amount {
-                deposit.is_disputed = true;
-                self.available -= deposit.amount;
-                self.held += deposit.amount;
+            // a disputed withdrawal is provisionally reinstated: the money
+            // never left `held`/`total`'s perspective while the dispute stands
+            TxKind::Withdrawal => {
+                self.held += amount;
+                self.total += amount;
             }
         }
+        Ok(())
     }
     // resolves and chargebacks are handled here
-    fn resolve_dispute(&mut self, tx: u32, is_chargeback: bool) {
-        if let Some(deposit) = self.deposits.iter_mut().find(|d| d.tx == tx) {
-            if !deposit.is_disputed || deposit.is_dispute_handled {
-                return
-            }
-            deposit.is_dispute_handled = true;
-            self.held -= deposit.amount;
-
-            if !is_chargeback {
-                self.available += deposit.amount;
-            } else {
-                self.total -= deposit.amount;
+    fn resolve_dispute(&mut self, tx: u32, is_chargeback: bool) -> Result<(), LedgerError> {
+        let transaction = self.transactions.iter_mut().find(|t| t.tx == tx).ok_or(LedgerError::UnknownTx)?;
+        if transaction.state != TxState::Disputed {
+            return Err(LedgerError::NotDisputed);
+        }
+        let amount = transaction.amount;
+        let kind = transaction.kind;
+        transaction.state = if is_chargeback { TxState::ChargedBack } else { TxState::Resolved };
+        self.held -= amount;
+
+        match (kind, is_chargeback) {
+            // deposit confirmed legitimate: release the held funds back to available
+            (TxKind::Deposit, false) => self.available += amount,
+            // deposit confirmed fraudulent: claw it back out of the account entirely
+            (TxKind::Deposit, true) => {
+                self.total -= amount;
                 self.locked = true;
             }
+            // either verdict reinstates the withdrawn funds; a chargeback additionally freezes the account
+            (TxKind::Withdrawal, chargeback) => {
+                self.available += amount;
+                if chargeback {
+                    self.locked = true;
+                }
+            }
         }
+        Ok(())
     }
 
     // handle record and process transaction based on the operation
-    pub fn handle_record(&mut self, record: &Record, should_block_locked: bool) {
+    pub fn handle_record(&mut self, record: &Record, should_block_locked: bool) -> Result<(), LedgerError> {
         if should_block_locked && self.locked {
-            return
+            return Err(LedgerError::FrozenAccount);
         }
 
         match &record.r#type {
             Operation::Deposit => {
                 let amount = record.amount.expect("Expecting an amount from record");
                 self.deposit_amount(amount);
-                self.deposits.push(ClientDeposit::from_record(record));
+                self.transactions.push(ClientTx::from_record(record));
+                Ok(())
             }
             Operation::Withdrawal => {
                 let amount = record.amount.expect("Expecting an amount from record");
-                self.withdraw_amount(amount);
+                if self.withdraw_amount(amount) {
+                    self.transactions.push(ClientTx::from_record(record));
+                }
+                Ok(())
             }
             Operation::Dispute => self.new_dispute(record.tx),
             Operation::Resolve => self.resolve_dispute(record.tx, false),
@@ -173,13 +283,18 @@ impl fmt::Display for BadRecordForClientCreation {
     }
 }
 
+#[cfg(test)]
+fn dec(s: &str) -> Decimal {
+    s.parse().unwrap()
+}
+
 #[test]
 fn record_validity() {
     // a simple deposit record
     let mut r = Record {
         r#type: Operation::Deposit,
         client: 1,
-        amount: Some(1.0),
+        amount: Some(dec("1")),
         tx: 1
     };
     assert!(r.is_valid());
@@ -189,7 +304,7 @@ fn record_validity() {
     assert!(c.is_ok());
 
     // amount must be strictly greater than 0
-    r.amount = Some(0.0);
+    r.amount = Some(Decimal::ZERO);
     assert!(!r.is_valid());
 
     // the amount should be present as well...
@@ -209,33 +324,33 @@ fn check_operations() {
     let mut r = Record {
         r#type: Operation::Deposit,
         client: 1,
-        amount: Some(3.0),
+        amount: Some(dec("3")),
         tx: 1
     };
     // valid init
     let mut c = Client::from_record(&r).unwrap();
     assert_eq!(c.total, r.amount.unwrap());
     assert_eq!(c.total, c.available);
-    assert_eq!(c.held, 0.0);
+    assert_eq!(c.held, Decimal::ZERO);
     assert!(!c.locked);
 
     // deposit
-    c.handle_record(&r, false);
-    assert_eq!(c.total, r.amount.unwrap() * 2.0);
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.total, dec("6"));
 
     // withdrawal
-    r.amount = Some(0.5);
+    r.amount = Some(dec("0.5"));
     r.r#type = Operation::Withdrawal;
-    c.handle_record(&r, false);
-    assert_eq!(c.total, 5.5);
-    assert_eq!(c.available, 5.5);
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.total, dec("5.5"));
+    assert_eq!(c.available, dec("5.5"));
 
     // ignore withdrawal if not enough funds
-    r.amount = Some(10.0);
+    r.amount = Some(dec("10"));
     r.r#type = Operation::Withdrawal;
-    c.handle_record(&r, false);
-    assert_eq!(c.total, 5.5);
-    assert_eq!(c.available, 5.5);
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.total, dec("5.5"));
+    assert_eq!(c.available, dec("5.5"));
 
     // validity check
     assert_eq!(c.total, c.available + c.held);
@@ -246,74 +361,127 @@ fn check_disputes() {
     let mut r = Record {
         r#type: Operation::Deposit,
         client: 1,
-        amount: Some(3.0),
+        amount: Some(dec("3")),
         tx: 1
     };
     let mut c = Client::from_record(&r).unwrap();
     r.tx = 2;
-    c.handle_record(&r, false);
+    c.handle_record(&r, false).unwrap();
     r.tx = 3;
-    c.handle_record(&r, false);
+    c.handle_record(&r, false).unwrap();
 
-    // ignore chargeback if no dispute
+    // reject chargeback if no dispute
     r.tx = 1;
     r.r#type = Operation::Chargeback;
-    c.handle_record(&r, false);
-    assert_eq!(c.held, 0.0);
+    assert_eq!(c.handle_record(&r, false), Err(LedgerError::NotDisputed));
+    assert_eq!(c.held, Decimal::ZERO);
 
     r.r#type = Operation::Dispute;
-    c.handle_record(&r, false);
-    assert_eq!(c.held, 3.0);
-    assert_eq!(c.total, 9.0);
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.held, dec("3"));
+    assert_eq!(c.total, dec("9"));
     assert_eq!(c.total, c.available + c.held);
 
     // valid chargeback
     r.r#type = Operation::Chargeback;
-    c.handle_record(&r, false);
-    assert_eq!(c.held, 0.0);
-    assert_eq!(c.total, 6.0);
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.held, Decimal::ZERO);
+    assert_eq!(c.total, dec("6"));
     assert_eq!(c.total, c.available + c.held);
     assert!(c.locked);
     c.locked = false;
 
     // dispute already handled for the given transaction
     r.r#type = Operation::Dispute;
-    c.handle_record(&r, false);
-    assert_eq!(c.held, 0.0);
+    assert_eq!(c.handle_record(&r, false), Err(LedgerError::AlreadyDisputed));
+    assert_eq!(c.held, Decimal::ZERO);
     assert_eq!(c.total, c.available + c.held);
 
     r.tx = 2;
     r.r#type = Operation::Dispute;
-    c.handle_record(&r, false);
-    assert_eq!(c.held, 3.0);
-    assert_eq!(c.total, 6.0);
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.held, dec("3"));
+    assert_eq!(c.total, dec("6"));
 
     // resolve the dispute
     r.r#type = Operation::Resolve;
-    c.handle_record(&r, false);
-    assert_eq!(c.held, 0.0);
-    assert_eq!(c.total, 6.0);
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.held, Decimal::ZERO);
+    assert_eq!(c.total, dec("6"));
     assert_eq!(c.total, c.available + c.held);
 
     r.tx = 4;
     r.r#type = Operation::Deposit;
-    c.handle_record(&r, false);
+    c.handle_record(&r, false).unwrap();
 
     r.r#type = Operation::Withdrawal;
-    r.amount = Some(9.0);
-    c.handle_record(&r, false);
-    assert_eq!(c.total, 0.0);
+    r.amount = Some(dec("9"));
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.total, Decimal::ZERO);
 
     // no available amount for a dispute
     r.r#type = Operation::Dispute;
-    c.handle_record(&r, false);
-    assert_eq!(c.held, 0.0);
+    assert_eq!(c.handle_record(&r, false), Err(LedgerError::NotEnoughFunds));
+    assert_eq!(c.held, Decimal::ZERO);
     assert_eq!(c.total, c.available + c.held);
 
-    // ignore operation if account is locked
+    // reject operation if account is locked
     r.tx = 5;
     r.r#type = Operation::Deposit;
     c.locked = true;
-    c.handle_record(&r, true);
-    assert_eq!(c.total, 0.0);
+    assert_eq!(c.handle_record(&r, true), Err(LedgerError::FrozenAccount));
+    assert_eq!(c.total, Decimal::ZERO);
+
+    // reject a dispute for a transaction that was never processed
+    c.locked = false;
+    r.r#type = Operation::Dispute;
+    r.tx = 42;
+    assert_eq!(c.handle_record(&r, false), Err(LedgerError::UnknownTx));
+}
+
+#[test]
+fn check_withdrawal_disputes() {
+    let mut r = Record {
+        r#type: Operation::Deposit,
+        client: 1,
+        amount: Some(dec("10")),
+        tx: 1
+    };
+    let mut c = Client::from_record(&r).unwrap();
+
+    r.tx = 2;
+    r.r#type = Operation::Withdrawal;
+    r.amount = Some(dec("4"));
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.available, dec("6"));
+    assert_eq!(c.total, dec("6"));
+
+    // disputing a withdrawal moves it into held without touching available
+    r.r#type = Operation::Dispute;
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.available, dec("6"));
+    assert_eq!(c.held, dec("4"));
+    assert_eq!(c.total, dec("10"));
+    assert_eq!(c.total, c.available + c.held);
+
+    // resolving releases the disputed withdrawal back to available
+    r.r#type = Operation::Resolve;
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.available, dec("10"));
+    assert_eq!(c.held, Decimal::ZERO);
+    assert_eq!(c.total, dec("10"));
+    assert!(!c.locked);
+
+    // a chargeback on a withdrawal also reinstates the funds, but locks the account
+    r.tx = 3;
+    r.r#type = Operation::Withdrawal;
+    c.handle_record(&r, false).unwrap();
+    r.r#type = Operation::Dispute;
+    c.handle_record(&r, false).unwrap();
+    r.r#type = Operation::Chargeback;
+    c.handle_record(&r, false).unwrap();
+    assert_eq!(c.available, dec("10"));
+    assert_eq!(c.held, Decimal::ZERO);
+    assert_eq!(c.total, dec("10"));
+    assert!(c.locked);
 }